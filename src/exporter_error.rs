@@ -0,0 +1,16 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum ExporterError {
+    ParseError(String),
+}
+
+impl fmt::Display for ExporterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExporterError::ParseError(msg) => write!(f, "parse error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ExporterError {}
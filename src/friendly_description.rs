@@ -0,0 +1,18 @@
+use std::borrow::Cow;
+
+/// How a peer's friendly name was derived, so callers can tell a configured
+/// name apart from a config-file comment if they ever need to.
+#[derive(Debug, Clone)]
+pub enum FriendlyDescription<'a> {
+    Name(Cow<'a, str>),
+    Comment(Cow<'a, str>),
+}
+
+impl<'a> FriendlyDescription<'a> {
+    pub fn as_str(&self) -> &str {
+        match self {
+            FriendlyDescription::Name(s) => s,
+            FriendlyDescription::Comment(s) => s,
+        }
+    }
+}
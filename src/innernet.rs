@@ -0,0 +1,34 @@
+use crate::exporter_error::ExporterError;
+use rusqlite::Connection;
+use std::collections::HashMap;
+
+/// Reads `public_key -> name` pairs from an innernet peer database's
+/// `peers` table, as a peer name source alongside WireGuard config
+/// comments and the JSON names file.
+pub fn innernet_peer_names(path: &str) -> Result<HashMap<String, String>, ExporterError> {
+    let connection = Connection::open(path).map_err(|e| {
+        ExporterError::ParseError(format!("failed to open innernet db {}: {}", path, e))
+    })?;
+
+    let mut statement = connection
+        .prepare("SELECT public_key, name FROM peers")
+        .map_err(|e| {
+            ExporterError::ParseError(format!("failed to query innernet db {}: {}", path, e))
+        })?;
+
+    let rows = statement
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+        .map_err(|e| {
+            ExporterError::ParseError(format!("failed to read innernet db {}: {}", path, e))
+        })?;
+
+    let mut names = HashMap::new();
+    for row in rows {
+        let (public_key, name) = row.map_err(|e| {
+            ExporterError::ParseError(format!("failed to read innernet db row: {}", e))
+        })?;
+        names.insert(public_key, name);
+    }
+
+    Ok(names)
+}
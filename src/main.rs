@@ -1,5 +1,6 @@
 use anyhow::Context;
 use clap::{crate_authors, crate_name, crate_version, value_parser, Arg, ArgAction};
+use clap_complete::Shell;
 use hyper::{Body, Request};
 use log::{debug, info, trace};
 use prometheus_exporter_base::prelude::{Authorization, ServerOptions};
@@ -14,6 +15,9 @@ mod friendly_description;
 pub use friendly_description::*;
 use wireguard::WireGuard;
 mod exporter_error;
+mod innernet;
+mod stale_peer_hook;
+mod uapi;
 mod wireguard_config;
 use prometheus_exporter_base::render_prometheus;
 use std::net::IpAddr;
@@ -28,6 +32,14 @@ async fn perform_request(
         Some(interfaces_str) => interfaces_str.clone(),
         None => vec!["all".to_owned()],
     };
+    // the UAPI backend has no "all" concept of its own: `wg show` expands
+    // it for us, but talking to sockets directly means we have to find
+    // them ourselves.
+    let interfaces_to_handle = if options.use_uapi && interfaces_to_handle == ["all"] {
+        uapi::list_interfaces()?
+    } else {
+        interfaces_to_handle
+    };
     log::trace!("interfaces_to_handle == {:?}", interfaces_to_handle);
 
     let peer_entry_contents = options
@@ -54,15 +66,28 @@ async fn perform_request(
             "failed to parse peer names: expected JSON object mapping public keys to names"
         })?;
 
+    let innernet_peer_names = options
+        .innernet_db
+        .as_ref()
+        .map(|path| innernet::innernet_peer_names(path))
+        .transpose()?;
+
     let peer_entry_hashmap = peer_entry_contents
         .as_ref()
         .map(|contents| peer_entry_hashmap_try_from(contents))
         .transpose()?;
 
-    // Combine peer_entry_hashmap and more_peer_names into a single hashmap
-    let peer_entry_hashmap = match (peer_entry_hashmap, &more_peer_names) {
-        (Some(mut peer_entry_hashmap), Some(more_peer_names)) => {
-            peer_entry_hashmap.extend(more_peer_names.iter().map(|(public_key, friendly_name)| {
+    // Combine peer_entry_hashmap, more_peer_names and innernet_peer_names into a single
+    // hashmap. Each source is merged in turn, with later sources overriding earlier ones
+    // for the same public key.
+    let mut peer_entry_hashmap = peer_entry_hashmap;
+    for names in [more_peer_names.as_ref(), innernet_peer_names.as_ref()]
+        .into_iter()
+        .flatten()
+    {
+        peer_entry_hashmap
+            .get_or_insert_with(HashMap::new)
+            .extend(names.iter().map(|(public_key, friendly_name)| {
                 (
                     public_key.as_str(),
                     PeerEntry {
@@ -74,98 +99,114 @@ async fn perform_request(
                     },
                 )
             }));
-            Some(peer_entry_hashmap)
-        }
-        (Some(peer_entry_hashmap), None) => Some(peer_entry_hashmap),
-        (None, Some(more_peer_names)) => Some(
-            more_peer_names
-                .iter()
-                .map(|(public_key, friendly_name)| {
-                    (
-                        public_key.as_str(),
-                        PeerEntry {
-                            public_key,
-                            allowed_ips: "",
-                            friendly_description: Some(FriendlyDescription::Name(Cow::Borrowed(
-                                friendly_name,
-                            ))),
-                        },
-                    )
-                })
-                .collect(),
-        ),
-        (None, None) => None,
-    };
+    }
 
     trace!("peer_entry_hashmap == {:#?}", peer_entry_hashmap);
 
-    let mut wg_accumulator: Option<WireGuard> = None;
+    // Starts empty rather than `None` so that a UAPI scrape with no
+    // interfaces currently up (`list_interfaces()` returns an empty Vec)
+    // renders an empty metrics set instead of having nothing to unwrap.
+    let mut wg_accumulator = WireGuard::default();
 
     for interface_to_handle in interfaces_to_handle {
-        let output = if options.prepend_sudo {
-            Command::new("sudo")
-                .arg("wg")
-                .arg("show")
-                .arg(&interface_to_handle)
-                .arg("dump")
-                .output()?
+        let wg = if options.use_uapi {
+            uapi::wireguard_try_from_uapi(&interface_to_handle)?
         } else {
-            Command::new("wg")
-                .arg("show")
-                .arg(&interface_to_handle)
-                .arg("dump")
-                .output()?
-        };
+            let output = if options.prepend_sudo {
+                Command::new("sudo")
+                    .arg("wg")
+                    .arg("show")
+                    .arg(&interface_to_handle)
+                    .arg("dump")
+                    .output()?
+            } else {
+                Command::new("wg")
+                    .arg("show")
+                    .arg(&interface_to_handle)
+                    .arg("dump")
+                    .output()?
+            };
 
-        let output_stdout_str = String::from_utf8(output.stdout)?;
-        trace!(
-            "wg show {} dump stdout == {}",
-            interface_to_handle,
-            output_stdout_str
-        );
-        let output_stderr_str = String::from_utf8(output.stderr)?;
-        trace!(
-            "wg show {} dump stderr == {}",
-            interface_to_handle,
-            output_stderr_str
-        );
+            let output_stdout_str = String::from_utf8(output.stdout)?;
+            trace!(
+                "wg show {} dump stdout == {}",
+                interface_to_handle,
+                output_stdout_str
+            );
+            let output_stderr_str = String::from_utf8(output.stderr)?;
+            trace!(
+                "wg show {} dump stderr == {}",
+                interface_to_handle,
+                output_stderr_str
+            );
 
-        // the output of wg show is different if we use all or we specify an interface.
-        // In the first case the first column will be the interface name. In the second case
-        // the interface name will be omitted. We need to compensate for the skew somehow (one
-        // column less in the second case). We solve this prepending the interface name in every
-        // line so the output of the second case will be equal to the first case.
-        let output_stdout_str = if interface_to_handle != "all" {
-            debug!("injecting {} to the wg show output", interface_to_handle);
-            let mut result = String::new();
-            for s in output_stdout_str.lines() {
-                result.push_str(&format!("{}\t{}\n", interface_to_handle, s));
-            }
-            result
-        } else {
-            output_stdout_str
-        };
+            // the output of wg show is different if we use all or we specify an interface.
+            // In the first case the first column will be the interface name. In the second case
+            // the interface name will be omitted. We need to compensate for the skew somehow (one
+            // column less in the second case). We solve this prepending the interface name in every
+            // line so the output of the second case will be equal to the first case.
+            let output_stdout_str = if interface_to_handle != "all" {
+                debug!("injecting {} to the wg show output", interface_to_handle);
+                let mut result = String::new();
+                for s in output_stdout_str.lines() {
+                    result.push_str(&format!("{}\t{}\n", interface_to_handle, s));
+                }
+                result
+            } else {
+                output_stdout_str
+            };
 
-        if let Some(wg_accumulator) = &mut wg_accumulator {
-            let wg = WireGuard::try_from(&output_stdout_str as &str)?;
-            wg_accumulator.merge(&wg);
-        } else {
-            wg_accumulator = Some(WireGuard::try_from(&output_stdout_str as &str)?);
+            WireGuard::try_from(&output_stdout_str as &str)?
         };
+
+        wg_accumulator.merge(&wg);
     }
 
-    if let Some(wg_accumulator) = wg_accumulator {
-        Ok(wg_accumulator.render_with_names(peer_entry_hashmap.as_ref(), &options))
-    } else {
-        panic!();
+    if let Some(hook_path) = &options.stale_peer_hook {
+        for (interface_name, interface) in &wg_accumulator.interfaces {
+            for peer in &interface.peers {
+                let delay_seconds = wireguard::handshake_delay_seconds(peer.latest_handshake);
+                let friendly_name = peer_entry_hashmap
+                    .as_ref()
+                    .and_then(|entries| entries.get(peer.public_key.as_str()))
+                    .and_then(|entry| entry.friendly_description.as_ref())
+                    .map(|description| description.as_str().to_owned())
+                    .unwrap_or_else(|| peer.public_key.clone());
+                let peer_key = format!("{}/{}", interface_name, peer.public_key);
+
+                options.stale_peer_tracker.check_and_run(
+                    &peer_key,
+                    peer.latest_handshake,
+                    delay_seconds,
+                    options.stale_threshold_seconds,
+                    || {
+                        stale_peer_hook::run_stale_peer_hook(
+                            hook_path,
+                            interface_name,
+                            &peer.public_key,
+                            &friendly_name,
+                            delay_seconds,
+                        )
+                    },
+                );
+            }
+        }
     }
+
+    Ok(wg_accumulator.render_with_names(peer_entry_hashmap.as_ref(), &options))
 }
 
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let matches = clap::Command::new(crate_name!())
+    let mut cmd = clap::Command::new(crate_name!())
         .version(crate_version!())
         .author(crate_authors!("\n"))
+        .arg(
+            Arg::new("completions")
+                .long("completions")
+                .value_parser(value_parser!(Shell))
+                .help("print a shell completion script for the given shell and exit")
+        )
         .arg(
             Arg::new("addr")
                 .short('l')
@@ -235,6 +276,18 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
                 .env("PROMETHEUS_WIREGUARD_EXPORTER_PEER_NAMES_CONFIG_FILE")
                 .help("If set, the exporter will look in the specified config file for mapping peer public keys to names.")
                 .action(ArgAction::Set))
+        .arg(
+            Arg::new("innernet_db")
+                .long("innernet-db")
+                .env("PROMETHEUS_WIREGUARD_EXPORTER_INNERNET_DB")
+                .help("If set, the exporter will look up peer names in the given innernet peer database.")
+                .action(ArgAction::Set))
+        .arg(
+            Arg::new("auth_token")
+                .long("auth-token")
+                .env("PROMETHEUS_WIREGUARD_EXPORTER_AUTH_TOKEN")
+                .help("If set, scrapes must present this value as an `Authorization: Bearer <token>` header.")
+                .action(ArgAction::Set))
         .arg(
             Arg::new("interfaces")
                 .short('i')
@@ -252,7 +305,36 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
                 .help("exports runtime calculated latest handshake delay")
                 .default_value("false")
         )
-         .get_matches();
+        .arg(
+            Arg::new("stale_peer_hook")
+                .long("stale-peer-hook")
+                .env("PROMETHEUS_WIREGUARD_EXPORTER_STALE_PEER_HOOK")
+                .help("If set, this script is run whenever a peer's handshake delay crosses --stale-threshold-seconds")
+                .action(ArgAction::Set))
+        .arg(
+            Arg::new("stale_threshold_seconds")
+                .long("stale-threshold-seconds")
+                .env("PROMETHEUS_WIREGUARD_EXPORTER_STALE_THRESHOLD_SECONDS")
+                .value_parser(value_parser!(u64))
+                .help("handshake delay, in seconds, after which a peer is considered stale")
+                .default_value("180")
+        )
+        .arg(
+            Arg::new("use_uapi")
+                .long("use-uapi")
+                .env("PROMETHEUS_WIREGUARD_EXPORTER_USE_UAPI")
+                .value_parser(value_parser!(bool))
+                .help("read peer state from the WireGuard userspace API socket instead of shelling out to wg show dump")
+                .default_value("false")
+        )
+        ;
+
+    let matches = cmd.clone().get_matches();
+
+    if let Some(shell) = matches.get_one::<Shell>("completions").copied() {
+        clap_complete::generate(shell, &mut cmd, crate_name!(), &mut std::io::stdout());
+        return Ok(());
+    }
 
     let options = Options::from_claps(&matches);
 
@@ -282,11 +364,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
 
     info!("starting exporter on http://{}/metrics", addr);
 
-    let server_options = ServerOptions {
-        addr,
-        authorization: Authorization::None,
+    let authorization = match &options.auth_token {
+        Some(token) => Authorization::Bearer(token.clone()),
+        None => Authorization::None,
     };
 
+    let server_options = ServerOptions { addr, authorization };
+
     render_prometheus(server_options, options, |request, options| {
         Box::pin(perform_request(request, options))
     })
@@ -0,0 +1,57 @@
+use crate::stale_peer_hook::StalePeerTracker;
+use clap::ArgMatches;
+
+/// Parsed command-line/environment options, threaded through the whole
+/// request lifecycle instead of re-reading `ArgMatches` everywhere.
+#[derive(Debug)]
+pub struct Options {
+    pub prepend_sudo: bool,
+    pub separate_allowed_ips: bool,
+    pub export_remote_ip_and_port: bool,
+    pub extract_names_config_files: Option<Vec<String>>,
+    pub peer_names_file: Option<String>,
+    pub innernet_db: Option<String>,
+    pub auth_token: Option<String>,
+    pub interfaces: Option<Vec<String>>,
+    pub export_latest_handshake_delay: bool,
+    pub use_uapi: bool,
+    pub stale_peer_hook: Option<String>,
+    pub stale_threshold_seconds: u64,
+    pub stale_peer_tracker: StalePeerTracker,
+    pub verbose: bool,
+}
+
+impl Options {
+    pub fn from_claps(matches: &ArgMatches) -> Self {
+        Options {
+            prepend_sudo: *matches.get_one("prepend_sudo").unwrap(),
+            separate_allowed_ips: *matches.get_one("separate_allowed_ips").unwrap(),
+            export_remote_ip_and_port: *matches.get_one("export_remote_ip_and_port").unwrap(),
+            extract_names_config_files: matches
+                .get_many::<String>("extract_names_config_files")
+                .map(|vals| vals.map(|s| s.to_owned()).collect()),
+            peer_names_file: matches
+                .get_one::<String>("peer_names_config_file")
+                .map(|s| s.to_owned()),
+            innernet_db: matches
+                .get_one::<String>("innernet_db")
+                .map(|s| s.to_owned()),
+            auth_token: matches
+                .get_one::<String>("auth_token")
+                .map(|s| s.to_owned()),
+            interfaces: matches
+                .get_many::<String>("interfaces")
+                .map(|vals| vals.map(|s| s.to_owned()).collect()),
+            export_latest_handshake_delay: *matches
+                .get_one("export_latest_handshake_delay")
+                .unwrap(),
+            use_uapi: *matches.get_one("use_uapi").unwrap(),
+            stale_peer_hook: matches
+                .get_one::<String>("stale_peer_hook")
+                .map(|s| s.to_owned()),
+            stale_threshold_seconds: *matches.get_one("stale_threshold_seconds").unwrap(),
+            stale_peer_tracker: StalePeerTracker::default(),
+            verbose: *matches.get_one("verbose").unwrap(),
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use log::{debug, warn};
+use std::collections::HashMap;
+use std::process::Command;
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct PeerHandshakeState {
+    /// The `latest_handshake` timestamp the hook was last fired for, so a
+    /// peer that stays stale on the same handshake doesn't re-trigger the
+    /// hook every scrape, while one that reconnects and then goes stale
+    /// again (a new, different `latest_handshake`) does.
+    notified_for_handshake: Option<u64>,
+}
+
+/// Tracks, across scrapes, the handshake timestamp each peer's hook last
+/// fired for, so we only run the hook once per stale handshake, rather
+/// than on every scrape while it stays stale.
+#[derive(Debug, Default)]
+pub struct StalePeerTracker {
+    state: Mutex<HashMap<String, PeerHandshakeState>>,
+}
+
+impl StalePeerTracker {
+    /// Records this scrape's handshake state for `peer_key` (typically
+    /// `"<interface>/<public_key>"`) and runs `hook` if the peer is stale
+    /// (`delay_seconds >= threshold_seconds`) and the hook hasn't already
+    /// fired for this exact `latest_handshake`.
+    pub fn check_and_run(
+        &self,
+        peer_key: &str,
+        latest_handshake: u64,
+        delay_seconds: u64,
+        threshold_seconds: u64,
+        hook: impl FnOnce(),
+    ) {
+        let is_stale = delay_seconds >= threshold_seconds;
+
+        let mut state = self.state.lock().unwrap();
+        let previous = state.entry(peer_key.to_owned()).or_default();
+
+        if is_stale && previous.notified_for_handshake != Some(latest_handshake) {
+            hook();
+            previous.notified_for_handshake = Some(latest_handshake);
+        } else if !is_stale {
+            previous.notified_for_handshake = None;
+        }
+    }
+}
+
+/// Invokes the configured hook script for a peer whose handshake delay
+/// just crossed the stale threshold, passing the interface, peer public
+/// key, resolved friendly name and handshake age both as arguments and
+/// as environment variables.
+pub fn run_stale_peer_hook(
+    hook_path: &str,
+    interface: &str,
+    public_key: &str,
+    friendly_name: &str,
+    delay_seconds: u64,
+) {
+    let child = Command::new(hook_path)
+        .arg(interface)
+        .arg(public_key)
+        .arg(friendly_name)
+        .arg(delay_seconds.to_string())
+        .env("WIREGUARD_EXPORTER_INTERFACE", interface)
+        .env("WIREGUARD_EXPORTER_PUBLIC_KEY", public_key)
+        .env("WIREGUARD_EXPORTER_FRIENDLY_NAME", friendly_name)
+        .env(
+            "WIREGUARD_EXPORTER_HANDSHAKE_AGE_SECONDS",
+            delay_seconds.to_string(),
+        )
+        .spawn();
+
+    match child {
+        Ok(mut child) => {
+            // spawn() doesn't reap the child on drop, so without an explicit
+            // wait (here, off the request path on its own thread) repeated
+            // firings would accumulate zombie processes.
+            let hook_path = hook_path.to_owned();
+            std::thread::spawn(move || match child.wait() {
+                Ok(status) => debug!("stale peer hook {} exited with {}", hook_path, status),
+                Err(e) => warn!("failed to wait on stale peer hook {}: {}", hook_path, e),
+            });
+        }
+        Err(e) => warn!("failed to run stale peer hook {}: {}", hook_path, e),
+    }
+}
@@ -0,0 +1,254 @@
+use crate::exporter_error::ExporterError;
+use crate::wireguard::{Interface, Peer, WireGuard};
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+
+const UAPI_SOCKET_DIR: &str = "/var/run/wireguard";
+
+/// Lists the interfaces that currently expose a UAPI socket, for the
+/// `all` case where there is no `wg show` to enumerate them for us.
+pub fn list_interfaces() -> Result<Vec<String>, ExporterError> {
+    let entries = std::fs::read_dir(UAPI_SOCKET_DIR).map_err(|e| {
+        ExporterError::ParseError(format!("failed to read {}: {}", UAPI_SOCKET_DIR, e))
+    })?;
+
+    let mut interfaces = Vec::new();
+    for entry in entries {
+        let entry = entry
+            .map_err(|e| ExporterError::ParseError(format!("failed to read directory entry: {}", e)))?;
+        if let Some(name) = entry.file_name().to_str().and_then(|name| name.strip_suffix(".sock")) {
+            interfaces.push(name.to_owned());
+        }
+    }
+
+    Ok(interfaces)
+}
+
+fn hex_to_base64(hex: &str) -> Result<String, ExporterError> {
+    if hex.len() % 2 != 0 {
+        return Err(ExporterError::ParseError(format!(
+            "odd-length hex key: {}",
+            hex
+        )));
+    }
+
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&hex[i..i + 2], 16)
+                .map_err(|_| ExporterError::ParseError(format!("invalid hex key: {}", hex)))
+        })
+        .collect::<Result<Vec<u8>, ExporterError>>()?;
+
+    Ok(base64::encode(bytes))
+}
+
+/// Reads peer state for a single interface directly from its WireGuard
+/// userspace API socket (`/var/run/wireguard/<interface>.sock`), as an
+/// alternative to shelling out to `wg show <interface> dump`.
+///
+/// See <https://www.wireguard.com/xplatform/> for the wire protocol: a
+/// `get=1\n\n` request is answered with newline-separated `key=value`
+/// pairs, device-level keys first, followed by one section per peer
+/// starting at its `public_key` line, terminated by a blank line and
+/// `errno=0`.
+pub fn wireguard_try_from_uapi(interface_name: &str) -> Result<WireGuard, ExporterError> {
+    let socket_path = format!("{}/{}.sock", UAPI_SOCKET_DIR, interface_name);
+
+    let mut stream = UnixStream::connect(&socket_path).map_err(|e| {
+        ExporterError::ParseError(format!("failed to connect to {}: {}", socket_path, e))
+    })?;
+
+    stream.write_all(b"get=1\n\n").map_err(|e| {
+        ExporterError::ParseError(format!("failed to write to {}: {}", socket_path, e))
+    })?;
+    // The server keeps the connection open for further requests; closing
+    // our write side lets it end the connection after this response.
+    stream.shutdown(std::net::Shutdown::Write).map_err(|e| {
+        ExporterError::ParseError(format!("failed to write to {}: {}", socket_path, e))
+    })?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).map_err(|e| {
+        ExporterError::ParseError(format!("failed to read from {}: {}", socket_path, e))
+    })?;
+
+    parse_uapi_response(interface_name, &response)
+}
+
+/// Parses a UAPI `get` response (the part after the `get=1\n\n` request)
+/// into a `WireGuard` for `interface_name`. Split out from
+/// `wireguard_try_from_uapi` so the parser can be exercised without a
+/// real socket.
+fn parse_uapi_response(interface_name: &str, response: &str) -> Result<WireGuard, ExporterError> {
+    let mut interface = Interface::default();
+    let mut current_peer: Option<Peer> = None;
+
+    for line in response.lines() {
+        if line.is_empty() || line == "errno=0" {
+            continue;
+        }
+
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| ExporterError::ParseError(format!("malformed uapi line: {}", line)))?;
+
+        match key {
+            "listen_port" => interface.listen_port = value.parse().ok(),
+            // The UAPI only reports the device's *private* key, never its
+            // public key directly (unlike `wg show dump`, which prints the
+            // public key in field 3 of the device line and lets
+            // `Interface.public_key` be populated from that). Deriving the
+            // public key here would mean doing our own Curve25519 scalar
+            // multiplication rather than pulling in a crypto dependency just
+            // for this, so `Interface.public_key` is intentionally left
+            // `None` for the UAPI backend; it isn't used by any exported
+            // metric today.
+            "private_key" => {}
+            "public_key" => {
+                if let Some(peer) = current_peer.take() {
+                    interface.peers.push(peer);
+                }
+                current_peer = Some(Peer {
+                    public_key: hex_to_base64(value)?,
+                    ..Default::default()
+                });
+            }
+            "preshared_key" => {
+                if let Some(peer) = current_peer.as_mut() {
+                    peer.preshared_key = if value.bytes().all(|b| b == b'0') {
+                        String::new()
+                    } else {
+                        hex_to_base64(value)?
+                    };
+                }
+            }
+            "endpoint" => {
+                if let Some(peer) = current_peer.as_mut() {
+                    peer.endpoint = Some(value.to_owned());
+                }
+            }
+            "persistent_keepalive_interval" => {
+                if let Some(peer) = current_peer.as_mut() {
+                    let interval: u32 = value.parse().unwrap_or(0);
+                    peer.persistent_keepalive = if interval == 0 { None } else { Some(interval) };
+                }
+            }
+            "last_handshake_time_sec" => {
+                if let Some(peer) = current_peer.as_mut() {
+                    peer.latest_handshake = value.parse().unwrap_or(0);
+                }
+            }
+            "last_handshake_time_nsec" => {
+                // Peer.latest_handshake only has second resolution, so
+                // combine the sec/nsec pair by rounding to the nearest
+                // second rather than dropping the ns component outright.
+                let nsec: u64 = value.parse().unwrap_or(0);
+                if let Some(peer) = current_peer.as_mut() {
+                    if nsec >= 500_000_000 {
+                        peer.latest_handshake = peer.latest_handshake.saturating_add(1);
+                    }
+                }
+            }
+            "rx_bytes" => {
+                if let Some(peer) = current_peer.as_mut() {
+                    peer.transfer_rx = value.parse().unwrap_or(0);
+                }
+            }
+            "tx_bytes" => {
+                if let Some(peer) = current_peer.as_mut() {
+                    peer.transfer_tx = value.parse().unwrap_or(0);
+                }
+            }
+            "allowed_ip" => {
+                if let Some(peer) = current_peer.as_mut() {
+                    peer.allowed_ips.push(value.to_owned());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(peer) = current_peer.take() {
+        interface.peers.push(peer);
+    }
+
+    let mut wg = WireGuard::default();
+    wg.interfaces.insert(interface_name.to_owned(), interface);
+    Ok(wg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_to_base64_round_trips_a_wireguard_key() {
+        let hex = "3ad51d8f45acffecf6a7c82ee21f0913aae07f7e4ac9b3f1f3e982b5c5cf3c11";
+        // 32 bytes needs 64 hex chars; trim to a valid even-length key.
+        let hex = &hex[..64];
+        let base64 = hex_to_base64(hex).unwrap();
+        let decoded = base64::decode(&base64).unwrap();
+        let expected = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect::<Vec<u8>>();
+        assert_eq!(decoded, expected);
+    }
+
+    #[test]
+    fn hex_to_base64_rejects_odd_length_input() {
+        assert!(hex_to_base64("abc").is_err());
+    }
+
+    #[test]
+    fn hex_to_base64_rejects_non_hex_input() {
+        assert!(hex_to_base64("zz").is_err());
+    }
+
+    #[test]
+    fn parses_device_and_peer_sections_from_a_uapi_fixture() {
+        let response = concat!(
+            "private_key=e84b5a6d2717c1003a13b431570353dbaca030812c2f9e5c4f8fb3c8b99d3a40\n",
+            "listen_port=51820\n",
+            "public_key=b8dd8fb4f85d3cb5d3a9a15b9e7f3d3e7f5f3b5e9c5b9f3d8a5c7f9b3d5f7a81\n",
+            "preshared_key=0000000000000000000000000000000000000000000000000000000000000000\n",
+            "endpoint=198.51.100.1:51820\n",
+            "last_handshake_time_sec=1700000000\n",
+            "last_handshake_time_nsec=900000000\n",
+            "persistent_keepalive_interval=0\n",
+            "rx_bytes=100\n",
+            "tx_bytes=200\n",
+            "allowed_ip=10.0.0.2/32\n",
+            "public_key=f36ee24b7c56aa064661b383703dbc24b937f0be782b33b12ca26a73181b4183\n",
+            "preshared_key=1111111111111111111111111111111111111111111111111111111111111111\n",
+            "persistent_keepalive_interval=25\n",
+            "rx_bytes=0\n",
+            "tx_bytes=0\n",
+            "allowed_ip=10.0.0.3/32\n",
+            "allowed_ip=10.0.0.4/32\n",
+            "errno=0\n",
+        );
+
+        let wg = parse_uapi_response("wg0", response).unwrap();
+        let interface = wg.interfaces.get("wg0").unwrap();
+        assert_eq!(interface.listen_port, Some(51820));
+        assert_eq!(interface.peers.len(), 2);
+
+        let first = &interface.peers[0];
+        assert!(first.preshared_key.is_empty());
+        assert_eq!(first.endpoint.as_deref(), Some("198.51.100.1:51820"));
+        // 900_000_000 ns rounds the handshake up by a second.
+        assert_eq!(first.latest_handshake, 1_700_000_001);
+        assert_eq!(first.persistent_keepalive, None);
+        assert_eq!(first.allowed_ips, vec!["10.0.0.2/32".to_owned()]);
+
+        let second = &interface.peers[1];
+        assert!(!second.preshared_key.is_empty());
+        assert_eq!(second.persistent_keepalive, Some(25));
+        assert_eq!(
+            second.allowed_ips,
+            vec!["10.0.0.3/32".to_owned(), "10.0.0.4/32".to_owned()]
+        );
+    }
+}
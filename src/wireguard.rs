@@ -0,0 +1,256 @@
+use crate::exporter_error::ExporterError;
+use crate::options::Options;
+use crate::wireguard_config::PeerEntry;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt::Write as _;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Seconds elapsed since `latest_handshake` (a unix timestamp), for the
+/// stale-peer hook check only; `wireguard_latest_handshake_seconds`
+/// itself still reports the raw timestamp. Returns `u64::MAX` for a peer
+/// that never handshaked, so it is always treated as stale.
+pub fn handshake_delay_seconds(latest_handshake: u64) -> u64 {
+    if latest_handshake == 0 {
+        return u64::MAX;
+    }
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+
+    now.saturating_sub(latest_handshake)
+}
+
+/// One peer as reported by `wg show <iface> dump`.
+#[derive(Debug, Clone, Default)]
+pub struct Peer {
+    pub public_key: String,
+    pub preshared_key: String,
+    pub endpoint: Option<String>,
+    pub allowed_ips: Vec<String>,
+    pub latest_handshake: u64,
+    pub transfer_rx: u64,
+    pub transfer_tx: u64,
+    pub persistent_keepalive: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Interface {
+    pub public_key: Option<String>,
+    pub listen_port: Option<u32>,
+    pub peers: Vec<Peer>,
+}
+
+/// All interfaces handled in a single scrape, keyed by interface name.
+#[derive(Debug, Clone, Default)]
+pub struct WireGuard {
+    pub interfaces: HashMap<String, Interface>,
+}
+
+impl WireGuard {
+    /// Folds `other`'s interfaces into `self`, as used when iterating
+    /// over several `wg show <iface> dump` invocations.
+    pub fn merge(&mut self, other: &WireGuard) {
+        for (name, interface) in &other.interfaces {
+            self.interfaces.insert(name.clone(), interface.clone());
+        }
+    }
+
+    pub fn render_with_names(
+        &self,
+        peer_entries: Option<&HashMap<&str, PeerEntry>>,
+        options: &Options,
+    ) -> String {
+        let friendly_name = |public_key: &str| -> String {
+            peer_entries
+                .and_then(|entries| entries.get(public_key))
+                .and_then(|entry| entry.friendly_description.as_ref())
+                .map(|description| description.as_str().to_owned())
+                .unwrap_or_else(|| public_key.to_owned())
+        };
+
+        let mut s = String::new();
+
+        // Emits one series per `(interface, peer, extra_labels)` combination for a
+        // single metric, with `value_for` supplying the sample value per peer.
+        // `extra_labels` fans a peer out into several series (for
+        // `separate_allowed_ips`) or adds labels to its single series (for
+        // `export_remote_ip_and_port`); see `peer_label_sets` below.
+        let mut emit = |name: &str, help: &str, metric_type: &str, value_for: &dyn Fn(&Peer) -> u64| {
+            writeln!(s, "# HELP {} {}", name, help).ok();
+            writeln!(s, "# TYPE {} {}", name, metric_type).ok();
+            for (interface_name, interface) in &self.interfaces {
+                for peer in &interface.peers {
+                    for extra_labels in peer_label_sets(peer, options) {
+                        writeln!(
+                            s,
+                            "{}{{interface=\"{}\",public_key=\"{}\",friendly_name=\"{}\"{}}} {}",
+                            name,
+                            interface_name,
+                            peer.public_key,
+                            friendly_name(&peer.public_key),
+                            extra_labels,
+                            value_for(peer)
+                        )
+                        .ok();
+                    }
+                }
+            }
+        };
+
+        emit(
+            "wireguard_sent_bytes_total",
+            "Bytes sent to the peer",
+            "counter",
+            &|peer| peer.transfer_tx,
+        );
+
+        emit(
+            "wireguard_received_bytes_total",
+            "Bytes received from the peer",
+            "counter",
+            &|peer| peer.transfer_rx,
+        );
+
+        emit(
+            "wireguard_latest_handshake_seconds",
+            "Seconds from the epoch of the last handshake",
+            "gauge",
+            &|peer| peer.latest_handshake,
+        );
+
+        if options.export_latest_handshake_delay {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or(0);
+            emit(
+                "wireguard_latest_handshake_delay_seconds",
+                "Seconds from the last handshake",
+                "gauge",
+                &|peer| now.saturating_sub(peer.latest_handshake),
+            );
+        }
+
+        emit(
+            "wireguard_persistent_keepalive_seconds",
+            "Configured persistent keepalive interval, 0 if off",
+            "gauge",
+            &|peer| peer.persistent_keepalive.unwrap_or(0) as u64,
+        );
+
+        emit(
+            "wireguard_preshared_key_configured",
+            "Whether the peer has a preshared key configured",
+            "gauge",
+            &|peer| if peer.preshared_key.is_empty() { 0 } else { 1 },
+        );
+
+        s
+    }
+}
+
+/// Builds the extra (beyond interface/public_key/friendly_name), already
+/// comma-prefixed label fragments for one peer's series, driven by
+/// `--separate_allowed_ips`/`-s` and `--export_remote_ip_and_port`/`-r`.
+///
+/// `-s` fans a peer's allowed IPs out into one series per IP (labelled
+/// `allowed_ip`) instead of the default single series with all of them
+/// joined into one `allowed_ips` label. `-r` adds `remote_ip`/`remote_port`
+/// labels parsed from the peer's endpoint, when it has one.
+fn peer_label_sets(peer: &Peer, options: &Options) -> Vec<String> {
+    let remote_labels = if options.export_remote_ip_and_port {
+        peer.endpoint
+            .as_deref()
+            .and_then(|endpoint| endpoint.rsplit_once(':'))
+            .map(|(ip, port)| format!(",remote_ip=\"{}\",remote_port=\"{}\"", ip, port))
+            .unwrap_or_default()
+    } else {
+        String::new()
+    };
+
+    if options.separate_allowed_ips {
+        if peer.allowed_ips.is_empty() {
+            vec![remote_labels]
+        } else {
+            peer.allowed_ips
+                .iter()
+                .map(|allowed_ip| format!("{},allowed_ip=\"{}\"", remote_labels, allowed_ip))
+                .collect()
+        }
+    } else {
+        vec![format!(
+            "{},allowed_ips=\"{}\"",
+            remote_labels,
+            peer.allowed_ips.join(",")
+        )]
+    }
+}
+
+impl TryFrom<&str> for WireGuard {
+    type Error = ExporterError;
+
+    /// Parses the (possibly interface-prefixed) tab-separated output of
+    /// `wg show <iface|all> dump`. Device lines carry 5 fields
+    /// (`interface`, `private_key`, `public_key`, `listen_port`, `fwmark`),
+    /// peer lines carry 9.
+    fn try_from(dump: &str) -> Result<Self, Self::Error> {
+        let mut wg = WireGuard::default();
+
+        for line in dump.lines() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split('\t').collect();
+
+            match fields.len() {
+                5 => {
+                    let interface = wg.interfaces.entry(fields[0].to_owned()).or_default();
+                    interface.public_key = if fields[2] == "(none)" {
+                        None
+                    } else {
+                        Some(fields[2].to_owned())
+                    };
+                    interface.listen_port = fields[3].parse().ok();
+                }
+                9 => {
+                    let interface = wg.interfaces.entry(fields[0].to_owned()).or_default();
+                    interface.peers.push(Peer {
+                        public_key: fields[1].to_owned(),
+                        preshared_key: if fields[2] == "(none)" {
+                            String::new()
+                        } else {
+                            fields[2].to_owned()
+                        },
+                        endpoint: if fields[3] == "(none)" {
+                            None
+                        } else {
+                            Some(fields[3].to_owned())
+                        },
+                        allowed_ips: fields[4].split(',').map(|ip| ip.trim().to_owned()).collect(),
+                        latest_handshake: fields[5].parse().unwrap_or(0),
+                        transfer_rx: fields[6].parse().unwrap_or(0),
+                        transfer_tx: fields[7].parse().unwrap_or(0),
+                        persistent_keepalive: if fields[8] == "off" {
+                            None
+                        } else {
+                            fields[8].parse().ok()
+                        },
+                    });
+                }
+                _ => {
+                    return Err(ExporterError::ParseError(format!(
+                        "unexpected number of fields ({}) in wg show dump line: {}",
+                        fields.len(),
+                        line
+                    )))
+                }
+            }
+        }
+
+        Ok(wg)
+    }
+}
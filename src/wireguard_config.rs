@@ -0,0 +1,82 @@
+use crate::exporter_error::ExporterError;
+use crate::friendly_description::FriendlyDescription;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/// A peer as known from a name source (WireGuard config comment, JSON
+/// names file, ...), keyed by public key in `peer_entry_hashmap`.
+#[derive(Debug, Clone)]
+pub struct PeerEntry<'a> {
+    pub public_key: &'a str,
+    pub allowed_ips: &'a str,
+    pub friendly_description: Option<FriendlyDescription<'a>>,
+}
+
+/// Extracts peer entries from one or more concatenated WireGuard config
+/// files. A peer's friendly name is taken from the last comment line
+/// (`#` or `;`) before its `PublicKey` line and after the previous peer's
+/// `PublicKey` line, whether that comment sits just before the `[Peer]`
+/// header or inside the section.
+pub fn peer_entry_hashmap_try_from(
+    contents: &str,
+) -> Result<HashMap<&str, PeerEntry>, ExporterError> {
+    let mut map = HashMap::new();
+    let mut in_peer = false;
+    let mut last_comment: Option<&str> = None;
+    let mut current_public_key: Option<&str> = None;
+
+    for line in contents.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        if let Some(comment) = trimmed.strip_prefix('#').or_else(|| trimmed.strip_prefix(';')) {
+            last_comment = Some(comment.trim());
+            continue;
+        }
+
+        if trimmed.eq_ignore_ascii_case("[Peer]") {
+            in_peer = true;
+            current_public_key = None;
+            continue;
+        }
+
+        if trimmed.starts_with('[') {
+            in_peer = false;
+            continue;
+        }
+
+        if !in_peer {
+            continue;
+        }
+
+        if let Some((key, value)) = trimmed.split_once('=') {
+            let key = key.trim();
+            let value = value.trim();
+
+            if key.eq_ignore_ascii_case("PublicKey") {
+                current_public_key = Some(value);
+                let friendly_description = last_comment
+                    .take()
+                    .map(|comment| FriendlyDescription::Comment(Cow::Borrowed(comment)));
+                map.insert(
+                    value,
+                    PeerEntry {
+                        public_key: value,
+                        allowed_ips: "",
+                        friendly_description,
+                    },
+                );
+            } else if key.eq_ignore_ascii_case("AllowedIPs") {
+                if let Some(public_key) = current_public_key {
+                    if let Some(entry) = map.get_mut(public_key) {
+                        entry.allowed_ips = value;
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(map)
+}